@@ -1,13 +1,41 @@
 use bevy::prelude::*;
 use bevy::render::pass::ClearColor;
 use rand::prelude::random;
-use bevy::core::FixedTimestep;
+use bevy::core::Timer;
 
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fmt;
 
-// game vars
-const WIDTH: u8 = 15;
-const HEIGHT: u8 = 15;
+// maximum number of buffered direction changes a player can queue up ahead of movement
+const INPUT_QUEUE_CAPACITY: usize = 2;
+
+// grid size, tick speed, window size and palette for a single game; override the
+// defaults and hand the result to `SnakePlugin` to run a different board/speed
+#[derive(Copy, Clone)]
+struct GameConfig {
+    width: u8,
+    height: u8,
+    tick: f64,
+    window_width: f32,
+    window_height: f32,
+    head_color: Color,
+    tail_color: Color,
+    food_color: Color,
+}
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self {
+            width: 16, // must stay even: the autopilot's Hamiltonian cycle relies on it
+            height: 16,
+            tick: 0.200,
+            window_width: 500.,
+            window_height: 500.,
+            head_color: Color::rgb(0.7, 0.7, 0.7),
+            tail_color: Color::rgb(0.3, 0.3, 0.3),
+            food_color: Color::rgb(0.7, 0., 0.),
+        }
+    }
+}
 
 #[derive(Default, Copy, Clone, Eq, PartialEq, Hash)]
 struct Position {
@@ -39,15 +67,15 @@ impl Size {
 
 #[derive(PartialEq, Copy, Clone)]
 enum Direction {
-    UP, DOWN, LEFT, RIGHT
+    Up, Down, Left, Right
 }
 impl Direction {
     fn opposite(self) -> Self {
         match self {
-            Self::LEFT => Self::RIGHT,
-            Self::RIGHT => Self::LEFT,
-            Self::UP => Self::DOWN,
-            Self::DOWN => Self::UP,
+            Self::Left => Self::Right,
+            Self::Right => Self::Left,
+            Self::Up => Self::Down,
+            Self::Down => Self::Up,
         }
     }
 }
@@ -60,15 +88,23 @@ pub enum SnakeState {
     Growth,
 }
 
+// overall game-loop state; gates which systems run and drives the
+// menu / restart / pause flow instead of an inline despawn-and-respawn
+#[derive(Debug, Hash, PartialEq, Eq, Clone, Copy)]
+enum AppState {
+    Menu,
+    Playing,
+    Paused,
+    GameOver,
+}
+
 // custom components as structs
 struct Food;
 struct SnakeHead {
     direction: Direction
 }
 struct SnakeSegment;
-struct QueuedDirection {
-    direction: Direction
-}
+struct QueuedDirections(VecDeque<Direction>);
 
 #[derive(Default)]
 struct SnakeSegments(Vec<Entity>); // list of snake parts
@@ -76,8 +112,13 @@ struct SnakeSegments(Vec<Entity>); // list of snake parts
 #[derive(Default)]
 struct LastTailPosition(Option<Position>);
 
-#[derive(Default, PartialEq, Eq)]
-struct OccupiedPositions(Vec<Position>); // [tail, ..., head]
+// drives the movement/eating/growth tick while Playing. A plain `Timer` rather than
+// `FixedTimestep` because a system can only carry one run criteria in Bevy 0.5, and
+// that slot is already spent on `State::on_update(AppState::Playing)`
+struct GameTimer(Timer);
+
+#[derive(Default)]
+struct OccupiedPositions(HashSet<Position>); // every grid cell currently covered by the snake
 
 // events
 struct SpawnFoodEvent;
@@ -91,80 +132,204 @@ struct Materials {
     food_material: Handle<ColorMaterial>,
 }
 
+// when on, the head's next direction comes from `autopilot_direction` instead of the keyboard
+#[derive(Default)]
+struct Autopilot(bool);
+
+// a fixed Hamiltonian cycle over every cell of the grid, used by the autopilot so
+// the snake can always make progress without ever trapping itself
+struct HamiltonianCycle {
+    order: Vec<Position>,            // order[i] = the cell visited at cycle index i
+    index: HashMap<Position, usize>, // cell -> its cycle index
+}
+impl HamiltonianCycle {
+    // builds the cycle by going up column 0, serpentining the remaining columns
+    // (rows 1 and up, alternating direction; the last column runs down to row 0),
+    // then closing the loop back along row 0.
+    // This construction needs an even grid width.
+    fn build(config: &GameConfig) -> Self {
+        assert_eq!(config.width % 2, 0, "autopilot's Hamiltonian cycle requires an even grid width");
+
+        let width = config.width as i8;
+        let height = config.height as i8;
+        let mut order = Vec::with_capacity(width as usize * height as usize);
+
+        for y in 0..height {
+            order.push(Position { x: 0, y });
+        }
+
+        let mut going_down = true;
+        for x in 1..width {
+            let is_last_column = x == width - 1;
+            let rows: Vec<i8> = if going_down {
+                if is_last_column { (0..height).rev().collect() } else { (1..height).rev().collect() }
+            } else {
+                (1..height).collect()
+            };
+            for y in rows {
+                order.push(Position { x, y });
+            }
+            going_down = !going_down;
+        }
+
+        for x in (1..width - 1).rev() {
+            order.push(Position { x, y: 0 });
+        }
+
+        let index = order.iter().enumerate().map(|(i, &pos)| (pos, i)).collect();
+        Self { order, index }
+    }
+}
+
+// bundles up the whole game (resources, state, systems, events) so it can be dropped into
+// any `App` with its own `GameConfig` for a different board size/speed/palette
+#[derive(Default)]
+pub struct SnakePlugin {
+    config: GameConfig,
+}
+impl Plugin for SnakePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.insert_resource(self.config)
+            .insert_resource(SnakeSegments::default())
+            .insert_resource(LastTailPosition::default())
+            .insert_resource(ClearColor(Color::rgb(0.2, 0.2, 0.25)))
+            .insert_resource(OccupiedPositions(HashSet::new()))
+            .insert_resource(Autopilot::default())
+            .insert_resource(HamiltonianCycle::build(&self.config))
+            .insert_resource(GameTimer(Timer::from_seconds(self.config.tick as f32, true)))
+            .add_startup_system(setup.system())
+            .add_event::<GrowthEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<SpawnFoodEvent>()
+            .add_state(AppState::Menu)
+            .add_system_set(
+                SystemSet::on_update(AppState::Menu).with_system(any_key_starts_game.system())
+            )
+            .add_system_set(
+                SystemSet::on_enter(AppState::Playing).with_system(enter_playing.system())
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Playing)
+                    .with_system(spawn_food.system().before(SnakeState::Input))
+                    .with_system(
+                        snake_input
+                            .system()
+                            .label(SnakeState::Input)
+                            .before(SnakeState::Movement) // ensures ::Input happens before ::Movement
+                    )
+                    .with_system(pause_input.system())
+                    .with_system(advance_game_timer.system().before(SnakeState::Movement))
+                    .with_system(snake_movement.system().label(SnakeState::Movement))
+                    .with_system(
+                        eat_food
+                            .system()
+                            .label(SnakeState::Eating)
+                            .after(SnakeState::Movement)
+                    )
+                    .with_system(
+                        snake_growth
+                            .system()
+                            .label(SnakeState::Growth)
+                            .after(SnakeState::Eating)
+                    )
+                    .with_system(game_over.system().after(SnakeState::Movement))
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Paused).with_system(pause_input.system())
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::GameOver).with_system(any_key_starts_game.system())
+            )
+            .add_system_set_to_stage(
+                CoreStage::PostUpdate,
+                SystemSet::new()
+                    .with_system(position_translation.system())
+                    .with_system(size_scaling.system())
+            );
+    }
+}
+
 fn main() {
+    let config = GameConfig::default();
+
     App::build()
         .insert_resource(WindowDescriptor {
             title: "Snake!".to_string(),
-            width: 500.,
-            height: 500.,
+            width: config.window_width,
+            height: config.window_height,
             ..Default::default()
         })
-        .insert_resource(SnakeSegments::default())
-        .insert_resource(LastTailPosition::default())
-        .insert_resource(ClearColor(Color::rgb(0.2, 0.2, 0.25)))
-        .insert_resource(OccupiedPositions(Vec::new()))
-        .add_startup_system(setup.system())
-        .add_startup_stage("game_setup", SystemStage::single(spawn_snake.system()))
-        .add_event::<GrowthEvent>()
-        .add_event::<GameOverEvent>()
-        .add_event::<SpawnFoodEvent>()
-        .add_system(spawn_food.system().before(SnakeState::Input))
-        // ! TEMPORARY
-        // .add_system(
-        //     spawn_food
-        //         .system()
-        //         .with_run_criteria(FixedTimestep::step(0.5))
-        // )
-        .add_system(
-            snake_input
-                .system()
-                .label(SnakeState::Input)
-                .before(SnakeState::Movement) // ensures ::Input happens before ::Movement
-        )
-        .add_system_set(
-            SystemSet::new()
-                .with_run_criteria(FixedTimestep::step(0.200))
-                .with_system(snake_movement.system().label(SnakeState::Movement))
-                .with_system(
-                    eat_food
-                        .system()
-                        .label(SnakeState::Eating)
-                        .after(SnakeState::Movement)
-                )
-                .with_system(
-                    snake_growth
-                        .system()
-                        .label(SnakeState::Growth)
-                        .after(SnakeState::Eating)
-                )
-        )
-        .add_system(game_over.system().after(SnakeState::Movement))
-        .add_system_set_to_stage(
-            CoreStage::PostUpdate,
-            SystemSet::new()
-                .with_system(position_translation.system())
-                .with_system(size_scaling.system())
-        )
         .add_plugins(DefaultPlugins)
+        .add_plugin(SnakePlugin { config })
         .run();
 }
 
 // system ran at startup
 fn setup(
     mut commands: Commands,
-    mut materials: ResMut<Assets<ColorMaterial>>
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<GameConfig>
 ) {
     // spawn new component: an orthographic 2d camera
     commands.spawn_bundle(OrthographicCameraBundle::new_2d());
 
+    println!("press any key to start");
+
     // create a new material
     commands.insert_resource(Materials {
-        head_material: materials.add(Color::rgb(0.7, 0.7, 0.7).into()),
-        tail_material: materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
-        food_material: materials.add(Color::rgb(0.7, 0., 0.).into()),
+        head_material: materials.add(config.head_color.into()),
+        tail_material: materials.add(config.tail_color.into()),
+        food_material: materials.add(config.food_color.into()),
     });
 }
 
+// transitions Menu/GameOver -> Playing on any key press
+fn any_key_starts_game(keyboard_input: Res<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keyboard_input.get_just_pressed().next().is_some() {
+        let _ = state.set(AppState::Playing);
+    }
+}
+
+// Space toggles Playing <-> Paused; while paused the fixed-timestep systems
+// simply don't run, since they only live in the Playing system set.
+// Paused is pushed on top of Playing and popped off again, so resuming does not
+// re-run `enter_playing`
+fn pause_input(mut keyboard_input: ResMut<Input<KeyCode>>, mut state: ResMut<State<AppState>>) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        // the state change re-runs the stage within this frame; consume the press so
+        // the other state's pause_input doesn't see it and toggle straight back
+        keyboard_input.reset(KeyCode::Space);
+        let current = *state.current();
+        let _ = match current {
+            AppState::Playing => state.push(AppState::Paused),
+            AppState::Paused => state.pop(),
+            _ => Ok(()),
+        };
+    }
+}
+
+// runs once on entering Playing (fresh start or restart after game over):
+// clears out whatever's left on the board and spawns a brand new snake
+fn enter_playing(
+    mut commands: Commands,
+    materials: Res<Materials>,
+    segments: ResMut<SnakeSegments>,
+    mut occupied: ResMut<OccupiedPositions>,
+    food_writer: EventWriter<SpawnFoodEvent>,
+    entities: Query<Entity, With<Size>>
+) {
+    for ent in entities.iter() {
+        commands.entity(ent).despawn();
+    }
+    occupied.0.clear();
+    spawn_snake(commands, materials, segments, occupied, food_writer);
+}
+
+// ticks the shared movement timer once per frame, ahead of everything it gates
+fn advance_game_timer(time: Res<Time>, mut timer: ResMut<GameTimer>) {
+    timer.0.tick(time.delta());
+}
+
 // system to spawn a snake head
 fn spawn_snake(
     mut commands: Commands,
@@ -181,19 +346,19 @@ fn spawn_snake(
                 ..Default::default()
             })
             .insert(SnakeHead {
-                direction: Direction::UP
+                direction: Direction::Up
             }) // add SnakeHead component
             .insert(Position { x: 0, y : 1}) // add Position component
             .insert(Size::square(0.8)) // add Size component of { .8, .8 }
-            .insert(QueuedDirection{ direction: Direction::UP })
+            .insert(QueuedDirections(VecDeque::with_capacity(INPUT_QUEUE_CAPACITY)))
             .id(),
         spawn_segment(
             commands, materials, Position{ x: 0, y: 0 }
         )
     ];
 
-    occupied.0.push(Position{ x: 0, y: 1 });
-    occupied.0.push(Position{ x: 0, y: 0 });
+    occupied.0.insert(Position{ x: 0, y: 1 });
+    occupied.0.insert(Position{ x: 0, y: 0 });
 
     food_writer.send(SpawnFoodEvent);
 }
@@ -203,73 +368,60 @@ fn spawn_food(
     mut commands: Commands,
     materials: Res<Materials>,
     occupied: Res<OccupiedPositions>,
+    config: Res<GameConfig>,
     mut food_reader: EventReader<SpawnFoodEvent>,
     mut game_over_writer: EventWriter<GameOverEvent>
 ) {
     if food_reader.iter().next().is_some() {
-        // println!("{:?}", occupied.0);
-        let mut spawn_found = false;
-        // only for debugging
-        // let mut tried_positions = Vec::new();
-
-        let mut rand_x = (random::<f32>() * WIDTH as f32) as i8;
-        let mut rand_y = (random::<f32>() * HEIGHT as f32) as i8;
-        // calculate new positions until unoccupied one is found
-        for _ in 0..(WIDTH as i16 * HEIGHT as i16 + 1) {
-            if occupied.0.iter().any(|&i| i == Position{ x: rand_x, y: rand_y }) {
-                // if position doesn't work, add it to attempts
-                // tried_positions.push(Position{ x: rand_x, y: rand_y });
-
-                let mut new_rand_x = (random::<f32>() * WIDTH as f32) as i8;
-                let mut new_rand_y = (random::<f32>() * HEIGHT as f32) as i8;
-                while new_rand_x == rand_x && new_rand_y == rand_y {
-                    new_rand_x = (random::<f32>() * WIDTH as f32) as i8;
-                    new_rand_y = (random::<f32>() * HEIGHT as f32) as i8;
-                }
+        let total_cells = config.width as u32 * config.height as u32;
+        let free_count = total_cells - occupied.0.len() as u32;
 
-                rand_x = new_rand_x;
-                rand_y = new_rand_y;
-            // if position hasn't been attempted
-            } else {
-                spawn_found = true;
-                // println!("spot found: {:?}", Position{ x: rand_x, y: rand_y });
-                break;
-            }
-        }
-
-        // if food can't spawn anywhere, game over
-        if !spawn_found {
+        // board is completely full: there's nowhere left for food to go, so the game is won
+        if free_count == 0 {
             println!("!YOU WIN!");
-            // println!("tried positions: {:?}", tried_positions);
             game_over_writer.send(GameOverEvent);
             return;
         }
 
+        // pick the k-th free cell (uniform over free cells), walking the grid to find it
+        let k = (random::<f32>() * free_count as f32) as u32;
+        let mut free_seen = 0;
+        let mut spawn_pos = Position::default();
+        'search: for x in 0..config.width as i8 {
+            for y in 0..config.height as i8 {
+                let pos = Position { x, y };
+                if !occupied.0.contains(&pos) {
+                    if free_seen == k {
+                        spawn_pos = pos;
+                        break 'search;
+                    }
+                    free_seen += 1;
+                }
+            }
+        }
+
         commands.spawn_bundle(SpriteBundle {
             material: materials.food_material.clone(),
             sprite: Sprite::new(Vec2::new(10., 10.)),
             ..Default::default()
         })
         .insert(Food)
-        .insert(Position {
-            x: rand_x,
-            y: rand_y,
-        })
+        .insert(spawn_pos)
         .insert(Size::square(0.65));
     }
 }
 
-fn size_scaling(windows: Res<Windows>, mut q: Query<(&Size, &mut Sprite)>) {
+fn size_scaling(windows: Res<Windows>, config: Res<GameConfig>, mut q: Query<(&Size, &mut Sprite)>) {
     let window = windows.get_primary().unwrap();
     for (sprite_size, mut sprite) in q.iter_mut() {
         sprite.size = Vec2::new(
-            sprite_size.width / (WIDTH as f32) * (window.width() as f32),
-            sprite_size.height / (HEIGHT as f32) * (window.height() as f32),
+            sprite_size.width / (config.width as f32) * window.width(),
+            sprite_size.height / (config.height as f32) * window.height(),
         );
     }
 }
 
-fn position_translation(windows: Res<Windows>, mut q: Query<(&Position, &mut Transform)>) {
+fn position_translation(windows: Res<Windows>, config: Res<GameConfig>, mut q: Query<(&Position, &mut Transform)>) {
     fn convert(pos: f32, bound_window: f32, bound_game: f32) -> f32 {
         let tile_size = bound_window / bound_game;
         pos / bound_game * bound_window - (bound_window / 2.) + (tile_size / 2.)
@@ -277,8 +429,8 @@ fn position_translation(windows: Res<Windows>, mut q: Query<(&Position, &mut Tra
     let window = windows.get_primary().unwrap();
     for (pos, mut transform) in q.iter_mut() {
         transform.translation = Vec3::new(
-            convert(pos.x as f32, window.width() as f32, WIDTH as f32),
-            convert(pos.y as f32, window.height() as f32, HEIGHT as f32),
+            convert(pos.x as f32, window.width(), config.width as f32),
+            convert(pos.y as f32, window.height(), config.height as f32),
             0.,
         );
     }
@@ -286,64 +438,169 @@ fn position_translation(windows: Res<Windows>, mut q: Query<(&Position, &mut Tra
 
 fn snake_input(
     keyboard_input: Res<Input<KeyCode>>,
-    mut heads: Query<(&SnakeHead, &mut QueuedDirection)>
+    mut autopilot: ResMut<Autopilot>,
+    cycle: Res<HamiltonianCycle>,
+    segments: Res<SnakeSegments>,
+    food_positions: Query<&Position, With<Food>>,
+    positions: Query<&Position>,
+    mut heads: Query<(Entity, &SnakeHead, &mut QueuedDirections)>
 ) {
-    if let Some((head, mut queued)) = heads.iter_mut().next() {
-        let dir: Direction = if keyboard_input.pressed(KeyCode::Left) {
-            Direction::LEFT
+    if keyboard_input.just_pressed(KeyCode::A) {
+        autopilot.0 = !autopilot.0;
+    }
+
+    if let Some((head_entity, head, mut queued)) = heads.iter_mut().next() {
+        let dir: Option<Direction> = if autopilot.0 {
+            // re-plan from where the head is now; a choice queued from an earlier cell is stale
+            queued.0.clear();
+            let head_pos = *positions.get(head_entity).unwrap();
+            let tail_pos = *positions.get(*segments.0.last().unwrap()).unwrap();
+            let food_pos = food_positions.iter().next().copied();
+            Some(autopilot_direction(&cycle, head_pos, tail_pos, food_pos))
+        } else if keyboard_input.pressed(KeyCode::Left) {
+            Some(Direction::Left)
         } else if keyboard_input.pressed(KeyCode::Down) {
-            Direction::DOWN
+            Some(Direction::Down)
         } else if keyboard_input.pressed(KeyCode::Up) {
-            Direction::UP
+            Some(Direction::Up)
         } else if keyboard_input.pressed(KeyCode::Right) {
-            Direction::RIGHT
+            Some(Direction::Right)
         } else {
-            queued.direction // defaults to previously queued input
+            None
         };
 
-        if dir != head.direction.opposite() {
-            queued.direction = dir;
+        if let Some(dir) = dir {
+            // validate against the last *enqueued* direction, not the last committed one,
+            // so a rapid sequence of key presses within one tick can't queue a reversal
+            let last_queued = queued.0.back().copied().unwrap_or(head.direction);
+
+            if dir == last_queued {
+                return; // drop duplicates of the tail of the queue
+            }
+
+            if dir != last_queued.opposite() && queued.0.len() < INPUT_QUEUE_CAPACITY {
+                queued.0.push_back(dir);
+            }
         }
     }
 }
 
+// normally follows the Hamiltonian cycle, but takes a shortcut toward the food
+// when doing so can't possibly trap the tail; always stays on the validation
+// path in `snake_input`, so it never reverses into the body either
+fn autopilot_direction(
+    cycle: &HamiltonianCycle,
+    head: Position,
+    tail: Position,
+    food: Option<Position>
+) -> Direction {
+    let total = cycle.order.len();
+    let forward = |from: usize, to: usize| (to + total - from) % total;
+
+    let head_idx = cycle.index[&head];
+    let tail_idx = cycle.index[&tail];
+    let food_idx = food.and_then(|pos| cycle.index.get(&pos).copied());
+    let dist_head_to_food = food_idx.map(|idx| forward(head_idx, idx));
+    let dist_head_to_tail = forward(head_idx, tail_idx);
+
+    let mut best: Option<(Direction, usize)> = None;
+
+    for &dir in [Direction::Up, Direction::Down, Direction::Left, Direction::Right].iter() {
+        let neighbor = step(head, dir);
+        let neighbor_idx = match cycle.index.get(&neighbor) {
+            Some(&idx) => idx,
+            None => continue, // off the board
+        };
+
+        let dist_head_to_neighbor = forward(head_idx, neighbor_idx);
+        if dist_head_to_neighbor >= dist_head_to_tail {
+            continue; // would reach or overtake the tail
+        }
+
+        if let Some(dist_to_food) = dist_head_to_food {
+            if dist_head_to_neighbor > dist_to_food {
+                continue; // would overshoot the food
+            }
+        }
+
+        let dist_neighbor_to_food = food_idx
+            .map(|idx| forward(neighbor_idx, idx))
+            .unwrap_or(dist_head_to_neighbor);
+
+        if best.is_none_or(|(_, best_dist)| dist_neighbor_to_food < best_dist) {
+            best = Some((dir, dist_neighbor_to_food));
+        }
+    }
+
+    // every shortcut was rejected (or there's nothing to shortcut toward):
+    // just keep following the cycle
+    best.map(|(dir, _)| dir)
+        .unwrap_or_else(|| direction_to(head, cycle.order[(head_idx + 1) % total]))
+}
+
+fn step(pos: Position, dir: Direction) -> Position {
+    match dir {
+        Direction::Up => Position { x: pos.x, y: pos.y + 1 },
+        Direction::Down => Position { x: pos.x, y: pos.y - 1 },
+        Direction::Left => Position { x: pos.x - 1, y: pos.y },
+        Direction::Right => Position { x: pos.x + 1, y: pos.y },
+    }
+}
+
+fn direction_to(from: Position, to: Position) -> Direction {
+    if to.x > from.x {
+        Direction::Right
+    } else if to.x < from.x {
+        Direction::Left
+    } else if to.y > from.y {
+        Direction::Up
+    } else {
+        Direction::Down
+    }
+}
+
 fn snake_movement(
+    timer: Res<GameTimer>,
     segments: ResMut<SnakeSegments>,
-    mut heads: Query<(Entity, &mut SnakeHead, &QueuedDirection)>,
+    config: Res<GameConfig>,
+    mut heads: Query<(Entity, &mut SnakeHead, &mut QueuedDirections)>,
     mut positions: Query<&mut Position>,
     mut occupied: ResMut<OccupiedPositions>,
     mut last_tail_position: ResMut<LastTailPosition>,
     mut game_over_writer: EventWriter<GameOverEvent>
 ) {
-    // println!("{:?}", occupied.0);
-    if let Some((head_entity, mut head, queued)) = heads.iter_mut().next() {
-        head.direction = queued.direction;
+    if !timer.0.finished() {
+        return;
+    }
+
+    if let Some((head_entity, mut head, mut queued)) = heads.iter_mut().next() {
+        head.direction = queued.0.pop_front().unwrap_or(head.direction);
         let segment_positions = segments.0.iter()
             .map(|e| *positions.get_mut(*e).unwrap())
             .collect::<Vec<Position>>();
         let mut head_pos = positions.get_mut(head_entity).unwrap();
         match &head.direction {
-            Direction::LEFT => {
+            Direction::Left => {
                 head_pos.x -= 1;
             }
-            Direction::RIGHT => {
+            Direction::Right => {
                 head_pos.x += 1;
             }
-            Direction::UP => {
+            Direction::Up => {
                 head_pos.y += 1;
             }
-            Direction::DOWN => {
+            Direction::Down => {
                 head_pos.y -= 1;
             }
         };
         if head_pos.x < 0
             || head_pos.y < 0
-            || head_pos.x as u8 >= WIDTH
-            || head_pos.y as u8 >= HEIGHT
+            || head_pos.x as u8 >= config.width
+            || head_pos.y as u8 >= config.height
         {
             game_over_writer.send(GameOverEvent);
         }
-        occupied.0.push(Position{ x: head_pos.x, y: head_pos.y }); // add new head
+        occupied.0.insert(Position{ x: head_pos.x, y: head_pos.y }); // head advances onto a new cell
         segment_positions
         .iter()
         .zip(segments.0.iter().skip(1))
@@ -359,17 +616,22 @@ fn snake_movement(
             game_over_writer.send(GameOverEvent);
         }
 
-        occupied.0.remove(0); // remove old tail
+        occupied.0.remove(&last_tail_position.0.unwrap()); // tail cell is vacated
     }
 }
 
 fn eat_food(
+    timer: Res<GameTimer>,
     mut commands: Commands,
     mut growth_writer: EventWriter<GrowthEvent>,
     mut food_writer: EventWriter<SpawnFoodEvent>,
     food_positions: Query<(Entity, &Position), With<Food>>,
     head_positions: Query<&Position, With<SnakeHead>>
 ) {
+    if !timer.0.finished() {
+        return;
+    }
+
     for head_pos in head_positions.iter() {
         for (ent, food_pos) in food_positions.iter() {
             if food_pos == head_pos {
@@ -382,6 +644,7 @@ fn eat_food(
 }
 
 fn snake_growth(
+    timer: Res<GameTimer>,
     commands: Commands,
     last_tail_position: Res<LastTailPosition>,
     mut segments: ResMut<SnakeSegments>,
@@ -389,13 +652,18 @@ fn snake_growth(
     mut occupied: ResMut<OccupiedPositions>,
     materials: Res<Materials>
 ) {
+    if !timer.0.finished() {
+        return;
+    }
+
     if growth_reader.iter().next().is_some() {
         segments.0.push(spawn_segment(
             commands,
             materials,
             last_tail_position.0.unwrap()
         ));
-        occupied.0.insert(0, last_tail_position.0.unwrap());
+        // snake grew instead of vacating, so the tail cell movement just freed is occupied again
+        occupied.0.insert(last_tail_position.0.unwrap());
     }
 }
 
@@ -416,19 +684,13 @@ fn spawn_segment(
 }
 
 fn game_over(
-    mut commands: Commands,
     mut reader: EventReader<GameOverEvent>,
-    food_writer: EventWriter<SpawnFoodEvent>,
-    materials: Res<Materials>,
-    segments_res: ResMut<SnakeSegments>,
-    mut occupied: ResMut<OccupiedPositions>,
-    entities: Query<Entity, With<Size>>
+    segments: Res<SnakeSegments>,
+    mut state: ResMut<State<AppState>>
 ) {
     if reader.iter().next().is_some() {
-        for ent in entities.iter() {
-            commands.entity(ent).despawn();
-        }
-        occupied.0.truncate(0);
-        spawn_snake(commands, materials, segments_res, occupied, food_writer);
+        println!("GAME OVER! final length: {}", segments.0.len());
+        println!("press any key to play again");
+        let _ = state.set(AppState::GameOver);
     }
-}
\ No newline at end of file
+}